@@ -15,18 +15,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         ORDER BY ASC(xsd:integer(?ordinal) )
     "#;
-    if let SparqlResult::Bindings(bindings) = cli.query(query)? {
-        for b in bindings {
-            let b = b?;
-            let doctor_label = b[1].as_ref().and_then(|t| t.lexical_form()).unwrap();
-            let performer_label = b[4]
-                .as_ref()
-                .and_then(|t| t.lexical_form())
-                .unwrap_or("NULL".into());
-            println!("{:?}\t{:?}", doctor_label, performer_label);
+    match cli.query(query)? {
+        SparqlResult::Bindings(bindings) => {
+            for b in bindings {
+                let b = b?;
+                let doctor_label = b[1].as_ref().and_then(|t| t.lexical_form()).unwrap();
+                let performer_label = b[4]
+                    .as_ref()
+                    .and_then(|t| t.lexical_form())
+                    .unwrap_or("NULL".into());
+                println!("{:?}\t{:?}", doctor_label, performer_label);
+            }
+        }
+        SparqlResult::Boolean(b) => println!("{}", b),
+        SparqlResult::Triples(triples) => {
+            for t in triples {
+                let [s, p, o] = t?;
+                println!("{:?} {:?} {:?} .", s, p, o);
+            }
         }
-    } else {
-        panic!("Unexpected results for the query.");
     }
     Ok(())
 }
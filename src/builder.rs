@@ -0,0 +1,521 @@
+//! A fluent, string-safe builder for `SELECT` queries, as an alternative to
+//! hand-writing SPARQL (which is easy to get subtly wrong, e.g. by forgetting
+//! to escape a literal).
+//!
+//! Entry point: [`crate::SparqlClient::select`].
+use super::Error;
+use crate::{check_iriref_chars, escape_literal, is_var_char, is_var_start, serialize_term};
+use sophia::sparql::{Query as SparqlQuery, ToQuery};
+use sophia::term::TTerm;
+
+/// A variable or bound value appearing in a [`TriplePattern`].
+///
+/// Built with [`Term::var`]/[`Term::iri`]/[`Term::prefixed`]/[`Term::a`], or
+/// from an existing RDF term with [`Term::bound`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// A SPARQL variable, rendered as `?name`.
+    Var(Box<str>),
+    /// Already-valid SPARQL term syntax (an IRIREF, a prefixed name, a
+    /// literal, `a`, ...), inserted verbatim.
+    Raw(Box<str>),
+}
+
+impl Term {
+    /// A variable (without its leading `?`).
+    ///
+    /// Fails if `name` is not a valid SPARQL variable name, which would
+    /// otherwise let it break out of its `?`-prefixed position in the
+    /// rendered query.
+    pub fn var(name: &str) -> Result<Self, Error> {
+        if !is_valid_name(name) {
+            return Err(Error::InvalidBinding(format!(
+                "not a valid SPARQL variable name: {}",
+                name
+            )));
+        }
+        Ok(Term::Var(Box::from(name)))
+    }
+
+    /// A full IRI, rendered as `<iri>`.
+    ///
+    /// Fails if `iri` contains characters forbidden in an `IRIREF`, which
+    /// would otherwise let it break out of the `<...>` it is rendered into.
+    pub fn iri(iri: &str) -> Result<Self, Error> {
+        check_iriref_chars(iri)?;
+        Ok(Term::Raw(format!("<{}>", iri).into_boxed_str()))
+    }
+
+    /// An already prefixed name (e.g. `"wdt:P31"`), or any other fragment of
+    /// SPARQL term syntax, inserted verbatim. See also [`Term::a`].
+    pub fn prefixed(name: &str) -> Self {
+        Term::Raw(Box::from(name))
+    }
+
+    /// The `a` keyword, shorthand for `rdf:type` as a predicate.
+    pub fn a() -> Self {
+        Term::Raw(Box::from("a"))
+    }
+
+    /// A plain string literal, escaped per the `STRING_LITERAL_QUOTE` grammar
+    /// rule.
+    pub fn literal(value: &str) -> Self {
+        Term::Raw(format!("\"{}\"", escape_literal(value)).into_boxed_str())
+    }
+
+    /// A language-tagged literal.
+    pub fn literal_lang(value: &str, lang: &str) -> Self {
+        Term::Raw(format!("\"{}\"@{}", escape_literal(value), lang).into_boxed_str())
+    }
+
+    /// A typed literal, with `datatype` given as a full IRI.
+    pub fn literal_dt(value: &str, datatype: &str) -> Self {
+        Term::Raw(format!("\"{}\"^^<{}>", escape_literal(value), datatype).into_boxed_str())
+    }
+
+    /// Render an existing RDF term (e.g. a [`sophia::term::BoxTerm`]) as
+    /// SPARQL term syntax, the same way [`crate::PreparedQuery::bind`] does.
+    pub fn bound<T: TTerm + ?Sized>(term: &T) -> Result<Self, Error> {
+        Ok(Term::Raw(serialize_term(term)?))
+    }
+
+    fn render(&self) -> Box<str> {
+        match self {
+            Term::Var(name) => format!("?{}", name).into_boxed_str(),
+            Term::Raw(text) => text.clone(),
+        }
+    }
+}
+
+/// A `subject predicate object` graph pattern, built with [`triple`].
+#[derive(Debug, Clone)]
+pub struct TriplePattern(Term, Term, Term);
+
+/// Build a [`TriplePattern`] from its subject, predicate and object.
+pub fn triple(s: Term, p: Term, o: Term) -> TriplePattern {
+    TriplePattern(s, p, o)
+}
+
+/// Whether `name` is a valid bare identifier (a SPARQL variable name or
+/// prefix label), i.e. would not break out of its `?`-prefixed or
+/// `PREFIX name:`-prefixed position in the rendered query.
+fn is_valid_name(name: &str) -> bool {
+    matches!(name.chars().next(), Some(c) if is_var_start(c)) && name.chars().all(is_var_char)
+}
+
+#[derive(Debug, Clone)]
+enum Element {
+    Triple(TriplePattern),
+    Optional(Vec<Element>),
+    Union(Vec<Vec<Element>>),
+    Filter(Box<str>),
+}
+
+/// A group of graph-pattern elements, as appearing inside a `WHERE` clause,
+/// an `OPTIONAL` block, or a `UNION` branch.
+///
+/// Built implicitly by [`SelectBuilder::optional`]/[`SelectBuilder::union`],
+/// which pass a fresh [`PatternBuilder`] to a closure.
+#[derive(Debug, Clone, Default)]
+pub struct PatternBuilder {
+    elements: Vec<Element>,
+}
+
+impl PatternBuilder {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a triple pattern to this group.
+    pub fn where_(mut self, t: TriplePattern) -> Self {
+        self.elements.push(Element::Triple(t));
+        self
+    }
+
+    /// Add a `FILTER` expression (inserted verbatim between its parentheses)
+    /// to this group.
+    pub fn filter(mut self, expr: &str) -> Self {
+        self.elements.push(Element::Filter(Box::from(expr)));
+        self
+    }
+
+    /// Nest an `OPTIONAL { ... }` block inside this group.
+    pub fn optional(mut self, build: impl FnOnce(PatternBuilder) -> PatternBuilder) -> Self {
+        self.elements
+            .push(Element::Optional(build(PatternBuilder::new()).elements));
+        self
+    }
+
+    /// Nest a `{ ... } UNION { ... } ...` block inside this group, one
+    /// branch per closure in `branches`.
+    pub fn union(
+        mut self,
+        branches: Vec<Box<dyn FnOnce(PatternBuilder) -> PatternBuilder>>,
+    ) -> Self {
+        let branches = branches
+            .into_iter()
+            .map(|build| build(PatternBuilder::new()).elements)
+            .collect();
+        self.elements.push(Element::Union(branches));
+        self
+    }
+}
+
+/// A fluent builder for a `SELECT` query, started from
+/// [`crate::SparqlClient::select`].
+///
+/// Implements [`ToQuery`], so a reference to it can be passed directly to
+/// [`crate::SparqlClient::query`].
+#[derive(Debug, Clone, Default)]
+pub struct SelectBuilder {
+    prefixes: Vec<(Box<str>, Box<str>)>,
+    distinct: bool,
+    vars: Vec<Box<str>>,
+    pattern: PatternBuilder,
+    order_by: Vec<Box<str>>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl SelectBuilder {
+    /// `vars` is stored as given and validated when the query is
+    /// [rendered](Self::build), the same way [`Self::prefix`]'s arguments
+    /// are.
+    pub(crate) fn new(vars: &[&str]) -> Self {
+        SelectBuilder {
+            vars: vars.iter().map(|v| Box::from(*v)).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Declare a `PREFIX`, for use in [`Term::prefixed`] names.
+    ///
+    /// Can be called repeatedly to declare several prefixes. `prefix`/`iri`
+    /// are validated when the query is [rendered](Self::build), the same
+    /// way [`Term::var`]/[`Term::iri`] validate their own arguments.
+    pub fn prefix(mut self, prefix: &str, iri: &str) -> Self {
+        self.prefixes.push((Box::from(prefix), Box::from(iri)));
+        self
+    }
+
+    /// Add the `DISTINCT` solution modifier.
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    /// Add a triple pattern to the query's `WHERE` clause.
+    pub fn where_(mut self, t: TriplePattern) -> Self {
+        self.pattern = self.pattern.where_(t);
+        self
+    }
+
+    /// Add a `FILTER` expression (inserted verbatim between its parentheses)
+    /// to the query's `WHERE` clause.
+    pub fn filter(mut self, expr: &str) -> Self {
+        self.pattern = self.pattern.filter(expr);
+        self
+    }
+
+    /// Nest an `OPTIONAL { ... }` block in the query's `WHERE` clause.
+    pub fn optional(mut self, build: impl FnOnce(PatternBuilder) -> PatternBuilder) -> Self {
+        self.pattern = self.pattern.optional(build);
+        self
+    }
+
+    /// Nest a `{ ... } UNION { ... } ...` block in the query's `WHERE`
+    /// clause, one branch per closure in `branches`.
+    pub fn union(
+        mut self,
+        branches: Vec<Box<dyn FnOnce(PatternBuilder) -> PatternBuilder>>,
+    ) -> Self {
+        self.pattern = self.pattern.union(branches);
+        self
+    }
+
+    /// Add an `ORDER BY` expression (e.g. `"?x"` or `"DESC(?x)"`), inserted
+    /// verbatim.
+    ///
+    /// Can be called repeatedly to order by several expressions.
+    pub fn order_by(mut self, expr: &str) -> Self {
+        self.order_by.push(Box::from(expr));
+        self
+    }
+
+    /// Add a `LIMIT` solution modifier.
+    pub fn limit(mut self, n: u64) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Add an `OFFSET` solution modifier.
+    pub fn offset(mut self, n: u64) -> Self {
+        self.offset = Some(n);
+        self
+    }
+
+    /// Render this builder as SPARQL query text.
+    ///
+    /// Fails if a variable name passed to [`crate::SparqlClient::select`] is
+    /// not a valid SPARQL variable name, or a prefix/IRI passed to
+    /// [`Self::prefix`] is not a valid prefix name/IRIREF — the same checks
+    /// [`Term::var`]/[`Term::iri`] apply to values inserted via
+    /// [`Self::where_`].
+    pub fn build(&self) -> Result<String, Error> {
+        let mut out = String::new();
+        for (prefix, iri) in &self.prefixes {
+            if !is_valid_name(prefix) {
+                return Err(Error::InvalidBinding(format!(
+                    "not a valid SPARQL prefix name: {}",
+                    prefix
+                )));
+            }
+            check_iriref_chars(iri)?;
+            out.push_str(&format!("PREFIX {}: <{}>\n", prefix, iri));
+        }
+        out.push_str("SELECT ");
+        if self.distinct {
+            out.push_str("DISTINCT ");
+        }
+        if self.vars.is_empty() {
+            out.push('*');
+        } else {
+            let vars: Vec<Box<str>> = self
+                .vars
+                .iter()
+                .map(|v| Term::var(v).map(|t| t.render()))
+                .collect::<Result<_, _>>()?;
+            out.push_str(&vars.join(" "));
+        }
+        out.push_str(" WHERE {\n");
+        render_elements(&self.pattern.elements, &mut out, 1);
+        out.push('}');
+        if !self.order_by.is_empty() {
+            out.push_str("\nORDER BY ");
+            out.push_str(&self.order_by.join(" "));
+        }
+        if let Some(n) = self.limit {
+            out.push_str(&format!("\nLIMIT {}", n));
+        }
+        if let Some(n) = self.offset {
+            out.push_str(&format!("\nOFFSET {}", n));
+        }
+        Ok(out)
+    }
+}
+
+fn render_elements(elements: &[Element], out: &mut String, indent: usize) {
+    let pad = "  ".repeat(indent);
+    for element in elements {
+        match element {
+            Element::Triple(TriplePattern(s, p, o)) => {
+                out.push_str(&format!(
+                    "{}{} {} {} .\n",
+                    pad,
+                    s.render(),
+                    p.render(),
+                    o.render()
+                ));
+            }
+            Element::Optional(inner) => {
+                out.push_str(&format!("{}OPTIONAL {{\n", pad));
+                render_elements(inner, out, indent + 1);
+                out.push_str(&format!("{}}}\n", pad));
+            }
+            Element::Union(branches) => {
+                for (i, branch) in branches.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(&format!("{}UNION\n", pad));
+                    }
+                    out.push_str(&format!("{}{{\n", pad));
+                    render_elements(branch, out, indent + 1);
+                    out.push_str(&format!("{}}}\n", pad));
+                }
+            }
+            Element::Filter(expr) => {
+                out.push_str(&format!("{}FILTER({})\n", pad, expr));
+            }
+        }
+    }
+}
+
+impl ToQuery<crate::Query> for &SelectBuilder {
+    fn to_query(self) -> Result<crate::Query, Error> {
+        crate::Query::parse(&self.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SparqlClient;
+
+    #[test]
+    fn select_star_empty_where() {
+        let q = SparqlClient::select(&[]).build().unwrap();
+        assert_eq!(q, "SELECT * WHERE {\n}");
+    }
+
+    #[test]
+    fn select_vars_single_triple() {
+        let q = SparqlClient::select(&["s", "o"])
+            .where_(triple(
+                Term::var("s").unwrap(),
+                Term::a(),
+                Term::var("o").unwrap(),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(q, "SELECT ?s ?o WHERE {\n  ?s a ?o .\n}");
+    }
+
+    #[test]
+    fn select_with_prefix_and_modifiers() {
+        let q = SparqlClient::select(&["doctor"])
+            .distinct()
+            .prefix("wdt", "http://www.wikidata.org/prop/direct/")
+            .prefix("wd", "http://www.wikidata.org/entity/")
+            .where_(triple(
+                Term::var("doctor").unwrap(),
+                Term::prefixed("wdt:P31"),
+                Term::prefixed("wd:Q47543030"),
+            ))
+            .order_by("?doctor")
+            .limit(10)
+            .offset(5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            q,
+            "PREFIX wdt: <http://www.wikidata.org/prop/direct/>\n\
+             PREFIX wd: <http://www.wikidata.org/entity/>\n\
+             SELECT DISTINCT ?doctor WHERE {\n\
+             \u{20}\u{20}?doctor wdt:P31 wd:Q47543030 .\n\
+             }\n\
+             ORDER BY ?doctor\n\
+             LIMIT 10\n\
+             OFFSET 5"
+        );
+    }
+
+    #[test]
+    fn select_optional_and_filter() {
+        let q = SparqlClient::select(&["doctor", "ordinal"])
+            .where_(triple(
+                Term::var("doctor").unwrap(),
+                Term::prefixed("wdt:P31"),
+                Term::prefixed("wd:Q47543030"),
+            ))
+            .optional(|p| {
+                p.where_(triple(
+                    Term::var("doctor").unwrap(),
+                    Term::prefixed("wdt:P1545"),
+                    Term::var("ordinal").unwrap(),
+                ))
+            })
+            .filter("?ordinal > 1")
+            .build()
+            .unwrap();
+        assert_eq!(
+            q,
+            "SELECT ?doctor ?ordinal WHERE {\n\
+             \u{20}\u{20}?doctor wdt:P31 wd:Q47543030 .\n\
+             \u{20}\u{20}OPTIONAL {\n\
+             \u{20}\u{20}\u{20}\u{20}?doctor wdt:P1545 ?ordinal .\n\
+             \u{20}\u{20}}\n\
+             \u{20}\u{20}FILTER(?ordinal > 1)\n\
+             }"
+        );
+    }
+
+    #[test]
+    fn select_union() {
+        let q = SparqlClient::select(&["x"])
+            .union(vec![
+                Box::new(|p: PatternBuilder| {
+                    p.where_(triple(
+                        Term::var("x").unwrap(),
+                        Term::a(),
+                        Term::prefixed(":A"),
+                    ))
+                }),
+                Box::new(|p: PatternBuilder| {
+                    p.where_(triple(
+                        Term::var("x").unwrap(),
+                        Term::a(),
+                        Term::prefixed(":B"),
+                    ))
+                }),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            q,
+            "SELECT ?x WHERE {\n\
+             \u{20}\u{20}{\n\
+             \u{20}\u{20}\u{20}\u{20}?x a :A .\n\
+             \u{20}\u{20}}\n\
+             \u{20}\u{20}UNION\n\
+             \u{20}\u{20}{\n\
+             \u{20}\u{20}\u{20}\u{20}?x a :B .\n\
+             \u{20}\u{20}}\n\
+             }"
+        );
+    }
+
+    #[test]
+    fn literal_terms_are_escaped() {
+        let t = Term::literal("a \"quote\"");
+        assert_eq!(t.render().as_ref(), "\"a \\\"quote\\\"\"");
+        assert_eq!(
+            Term::literal_lang("hi", "en").render().as_ref(),
+            "\"hi\"@en"
+        );
+        assert_eq!(
+            Term::literal_dt("42", "http://www.w3.org/2001/XMLSchema#integer")
+                .render()
+                .as_ref(),
+            "\"42\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+        );
+    }
+
+    #[test]
+    fn var_rejects_invalid_names() {
+        assert!(Term::var("doctor").is_ok());
+        assert!(Term::var("_blank").is_ok());
+        assert!(Term::var("").is_err());
+        assert!(Term::var("1x").is_err());
+        assert!(Term::var("x . } DELETE WHERE { ?s ?p ?o } #").is_err());
+    }
+
+    #[test]
+    fn iri_rejects_forbidden_chars() {
+        assert!(Term::iri("http://example.org/x").is_ok());
+        assert!(Term::iri("http://example.org/x> . } DELETE WHERE { ?s ?p ?o } #").is_err());
+    }
+
+    #[test]
+    fn iri_rejects_whitespace() {
+        assert!(Term::iri("http://example.org/ a b").is_err());
+        assert!(Term::iri("http://example.org/x\ty").is_err());
+    }
+
+    #[test]
+    fn build_rejects_invalid_select_vars() {
+        let err = SparqlClient::select(&["x } ; DROP ALL #"]).build();
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn build_rejects_invalid_prefix() {
+        let err = SparqlClient::select(&["x"])
+            .prefix("wd t", "http://example.org/")
+            .build();
+        assert!(err.is_err());
+
+        let err = SparqlClient::select(&["x"])
+            .prefix("wd", "http://example.org/x> . } DELETE WHERE { ?s ?p ?o } #")
+            .build();
+        assert!(err.is_err());
+    }
+}
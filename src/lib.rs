@@ -24,8 +24,8 @@
 //! if let SparqlResult::Bindings(bindings) = cli.query(query)? {
 //!     for b in bindings {
 //!         let b = b?;
-//!         let doctor_label = b[1].as_ref().unwrap().value();
-//!         let performer_label = b[4].as_ref().unwrap().value();
+//!         let doctor_label = b.get("doctorLabel").unwrap().value();
+//!         let performer_label = b.get("performerLabel").unwrap().value();
 //!         println!("{}\t{}", doctor_label, performer_label);
 //!     }
 //! }
@@ -36,21 +36,105 @@
 //! [Sophia]: https://docs.rs/sophia/
 use sophia::parser::{nt, turtle, xml};
 use sophia::sparql::{Query as SparqlQuery, SparqlBindings, SparqlDataset, SparqlResult, ToQuery};
-use sophia::term::{BoxTerm, CopyTerm};
+use sophia::term::{BoxTerm, CopyTerm, TTerm, TermKind};
 use sophia::triple::stream::TripleSource;
 use sophia::triple::Triple;
-use std::borrow::Borrow;
+use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
 use std::io::BufReader;
-use ureq::{Agent, Error as UreqError};
+use std::time::Duration;
+use ureq::{Agent, Error as UreqError, Request, Response};
 
+mod builder;
 mod results;
+mod results_json;
+mod results_xml;
+pub use builder::{triple, PatternBuilder, SelectBuilder, Term, TriplePattern};
 pub use results::BindingsDocument as Bindings;
+pub use results::Solution;
 use results::ResultsDocument;
 
 pub struct SparqlClient {
     endpoint: Box<str>,
+    update_endpoint: Option<Box<str>>,
     agent: Agent,
     accept: Option<String>,
+    transport: Transport,
+    auto_post_threshold: Option<usize>,
+    base_iri: Option<Box<str>>,
+    default_graphs: Vec<Box<str>>,
+    named_graphs: Vec<Box<str>>,
+    user_agent: Option<Box<str>>,
+    headers: Vec<(Box<str>, Box<str>)>,
+    auth: Option<Auth>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+/// HTTP authentication applied by a [`SparqlClient`] to every request it sends.
+///
+/// Set via [`SparqlClient::with_basic_auth`] or [`SparqlClient::with_bearer_auth`].
+#[derive(Debug, Clone)]
+enum Auth {
+    Basic { user: Box<str>, pass: Box<str> },
+    Bearer(Box<str>),
+}
+
+/// How a [`SparqlClient`] retries a request after a transient HTTP failure
+/// (`429 Too Many Requests` or `503 Service Unavailable`).
+///
+/// Set via [`SparqlClient::with_retry`]; defaults to [`RetryPolicy::none`].
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Never retry: the first `429`/`503` response is returned as an [`Error`].
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            backoff: Duration::from_secs(0),
+        }
+    }
+
+    /// Retry up to `max_retries` times, waiting `backoff * 2^attempt` between
+    /// attempts (exponential backoff).
+    pub fn exponential(max_retries: u32, backoff: Duration) -> Self {
+        RetryPolicy {
+            max_retries,
+            backoff,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// How a query is submitted to the endpoint, per the
+/// [query operation](https://www.w3.org/TR/sparql11-protocol/#query-operation)
+/// of the SPARQL 1.1 protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// `GET`, with the query (and dataset parameters) in the URL query string.
+    ///
+    /// Cacheable, but limited by the server's/proxy's maximum URL length.
+    Get,
+    /// `POST`, with the query as the request body (`application/sparql-query`).
+    PostDirect,
+    /// `POST`, with the query and dataset parameters form-encoded
+    /// (`application/x-www-form-urlencoded`).
+    PostForm,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::PostDirect
+    }
 }
 
 impl SparqlClient {
@@ -61,8 +145,19 @@ impl SparqlClient {
     pub fn new(endpoint: &str) -> Self {
         Self {
             endpoint: Box::from(endpoint),
+            update_endpoint: None,
             agent: Agent::new(),
             accept: None,
+            transport: Transport::default(),
+            auto_post_threshold: None,
+            base_iri: None,
+            default_graphs: Vec::new(),
+            named_graphs: Vec::new(),
+            user_agent: None,
+            headers: Vec::new(),
+            auth: None,
+            timeout: None,
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -72,6 +167,43 @@ impl SparqlClient {
         self
     }
 
+    /// Use a separate endpoint for [`update`](Self::update) requests.
+    ///
+    /// Useful for services (e.g. Oxigraph, Fuseki) that expose distinct
+    /// query and update URLs. When unset, updates are POSTed to the query
+    /// endpoint.
+    pub fn with_update_endpoint(mut self, endpoint: &str) -> Self {
+        self.update_endpoint = Some(Box::from(endpoint));
+        self
+    }
+
+    fn update_endpoint(&self) -> &str {
+        self.update_endpoint.as_deref().unwrap_or(&self.endpoint)
+    }
+
+    /// Execute a SPARQL 1.1 Update request (`INSERT DATA`, `DELETE DATA`,
+    /// `DELETE/INSERT ... WHERE`, `LOAD`, `CLEAR`, ...).
+    ///
+    /// POSTs `update` as `application/sparql-update`, per the
+    /// [update operation] of the SPARQL 1.1 protocol.
+    ///
+    /// [update operation]: https://www.w3.org/TR/sparql11-protocol/#update-operation
+    pub fn update(&self, update: &str) -> Result<(), Error> {
+        let resp = self.send_with_retry(|| {
+            let req = self
+                .apply_common(self.agent.post(self.update_endpoint()))
+                .set("Content-type", "application/sparql-update");
+            req.send_string(update)
+        });
+        match resp {
+            Ok(_) => Ok(()),
+            Err(UreqError::Status(404, _)) | Err(UreqError::Status(405, _)) => {
+                Err(Error::UpdateNotSupported)
+            }
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
     /// Replace the [Accept HTTP header](https://tools.ietf.org/html/rfc7231.html#section-5.3.2) used by this client.
     ///
     /// This might be useful if the endpoint implements content-negotation incorrectly.
@@ -87,6 +219,176 @@ impl SparqlClient {
         self.accept.as_deref().unwrap_or(Self::DEFAULT_ACCEPT)
     }
 
+    /// Set how queries are submitted to the endpoint.
+    ///
+    /// Defaults to [`Transport::PostDirect`].
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Set the base IRI against which relative references in the query are resolved.
+    ///
+    /// This is sent as a `BASE` prologue prepended to the query text, as per
+    /// the [SPARQL 1.1 query syntax](https://www.w3.org/TR/sparql11-query/#QSynIRI).
+    pub fn with_base_iri(mut self, iri: &str) -> Self {
+        self.base_iri = Some(Box::from(iri));
+        self
+    }
+
+    /// Add a `default-graph-uri` parameter, scoping the query's default graph.
+    ///
+    /// Can be called repeatedly to add several graphs; see the
+    /// [RDF dataset](https://www.w3.org/TR/sparql11-protocol/#dataset) section
+    /// of the SPARQL 1.1 protocol.
+    pub fn with_default_graph(mut self, iri: &str) -> Self {
+        self.default_graphs.push(Box::from(iri));
+        self
+    }
+
+    /// Add a `named-graph-uri` parameter, scoping the query's named graphs.
+    ///
+    /// Can be called repeatedly to add several graphs.
+    pub fn with_named_graph(mut self, iri: &str) -> Self {
+        self.named_graphs.push(Box::from(iri));
+        self
+    }
+
+    /// When using [`Transport::Get`], automatically submit the query via
+    /// `POST` instead once its text exceeds `max_len` bytes, to avoid
+    /// hitting a server's or proxy's maximum URL length (long Wikidata
+    /// queries are a common case).
+    pub fn with_auto_post_threshold(mut self, max_len: usize) -> Self {
+        self.auto_post_threshold = Some(max_len);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request.
+    ///
+    /// Public endpoints (e.g. Wikidata's) often reject generic user agents,
+    /// requiring instead a descriptive one identifying the application and a
+    /// contact URL or address; see their
+    /// [User-Agent policy](https://meta.wikimedia.org/wiki/User-Agent_policy).
+    pub fn with_user_agent<T: ToString>(mut self, user_agent: T) -> Self {
+        self.user_agent = Some(user_agent.to_string().into_boxed_str());
+        self
+    }
+
+    /// Add an arbitrary HTTP header sent with every request.
+    ///
+    /// Can be called repeatedly to add several headers.
+    pub fn with_header<T: ToString>(mut self, name: &str, value: T) -> Self {
+        self.headers
+            .push((Box::from(name), value.to_string().into_boxed_str()));
+        self
+    }
+
+    /// Authenticate every request with [HTTP Basic authentication](https://tools.ietf.org/html/rfc7617).
+    pub fn with_basic_auth(mut self, user: &str, pass: &str) -> Self {
+        self.auth = Some(Auth::Basic {
+            user: Box::from(user),
+            pass: Box::from(pass),
+        });
+        self
+    }
+
+    /// Authenticate every request with a [Bearer token](https://tools.ietf.org/html/rfc6750).
+    pub fn with_bearer_auth(mut self, token: &str) -> Self {
+        self.auth = Some(Auth::Bearer(Box::from(token)));
+        self
+    }
+
+    /// Set the timeout of every request sent by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the policy used to retry requests that fail with a transient
+    /// `429`/`503` HTTP status.
+    ///
+    /// Defaults to [`RetryPolicy::none`].
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Apply the `User-Agent`, extra headers, authentication and timeout
+    /// configured on this client to `req`.
+    fn apply_common(&self, mut req: Request) -> Request {
+        if let Some(user_agent) = &self.user_agent {
+            req = req.set("User-Agent", user_agent);
+        }
+        for (name, value) in &self.headers {
+            req = req.set(name, value);
+        }
+        req = match &self.auth {
+            Some(Auth::Basic { user, pass }) => req.set(
+                "Authorization",
+                &format!("Basic {}", base64_encode(&format!("{}:{}", user, pass))),
+            ),
+            Some(Auth::Bearer(token)) => req.set("Authorization", &format!("Bearer {}", token)),
+            None => req,
+        };
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        req
+    }
+
+    /// Run `send`, retrying it per [`Self::with_retry`] as long as it fails
+    /// with a `429`/`503` HTTP status.
+    fn send_with_retry<F>(&self, mut send: F) -> Result<Response, UreqError>
+    where
+        F: FnMut() -> Result<Response, UreqError>,
+    {
+        let mut attempt = 0;
+        loop {
+            match send() {
+                Err(UreqError::Status(429 | 503, _)) if attempt < self.retry.max_retries => {
+                    attempt += 1;
+                    std::thread::sleep(self.retry.backoff * 2u32.pow(attempt - 1));
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// The transport actually used for a query of length `query_len`,
+    /// honoring [`Self::with_auto_post_threshold`].
+    fn effective_transport(&self, query_len: usize) -> Transport {
+        match self.auto_post_threshold {
+            Some(max_len) if self.transport == Transport::Get && query_len > max_len => {
+                Transport::PostDirect
+            }
+            _ => self.transport,
+        }
+    }
+
+    /// Prepend a `BASE` prologue to `query` if a base IRI was set.
+    ///
+    /// Applied unconditionally to every [`ToQuery`] input, including a
+    /// rendered [`PreparedQuery`] that already carries its own `BASE`
+    /// prologue from [`Self::prepare`]'s `base_iri` argument; see the note
+    /// on [`Self::prepare`].
+    fn prepare_query_text<'q>(&self, query: &'q str) -> Cow<'q, str> {
+        match &self.base_iri {
+            Some(base) => Cow::Owned(format!("BASE <{}>\n{}", base, query)),
+            None => Cow::Borrowed(query),
+        }
+    }
+
+    /// Append the `default-graph-uri`/`named-graph-uri` parameters, if any, to `req`.
+    fn apply_graph_params(&self, mut req: Request) -> Request {
+        for g in &self.default_graphs {
+            req = req.query("default-graph-uri", g);
+        }
+        for g in &self.named_graphs {
+            req = req.query("named-graph-uri", g);
+        }
+        req
+    }
+
     fn wrap_triple_source<T: TripleSource + 'static>(
         triples: T,
     ) -> Result<SparqlResult<Self>, Error>
@@ -107,6 +409,37 @@ impl SparqlClient {
         );
         Ok(SparqlResult::Triples(it))
     }
+
+    /// Prepare `query` for repeated execution with different variable
+    /// bindings, optionally resolving relative IRIs against `base_iri`.
+    ///
+    /// Unlike [`SparqlDataset::prepare_query`] (a no-op for this client, see
+    /// [`Query`]), the returned [`PreparedQuery`] lets [`PreparedQuery::bind`]
+    /// substitute `?var`/`$var` placeholders with proper SPARQL term syntax,
+    /// so the same template can be reused across many entities (e.g. fetching
+    /// labels for a list of Wikidata Q-IDs) without hand-building strings.
+    ///
+    /// `base_iri` is independent from [`Self::with_base_iri`]: if both are
+    /// set, [`SparqlDataset::query`] prepends the client's `BASE` prologue
+    /// in front of the already-rendered query text, so the query ends up
+    /// with two stacked `BASE` prologues (the one from `base_iri` here
+    /// taking precedence for relative IRIs in the query body, since it
+    /// appears closer to them). Prefer setting only one of the two.
+    pub fn prepare(&self, query: &str, base_iri: Option<&str>) -> PreparedQuery {
+        PreparedQuery::new(query, base_iri)
+    }
+
+    /// Start building a `SELECT` query with [`SelectBuilder`], a fluent,
+    /// string-safe alternative to hand-writing SPARQL.
+    ///
+    /// `vars` names the projected variables (without their leading `?`); an
+    /// empty slice projects all variables (`SELECT *`). Each name is
+    /// validated as a proper SPARQL variable name by [`SelectBuilder::build`]
+    /// (the same check [`Term::var`] applies). Call [`SparqlClient::query`]
+    /// with a reference to the built [`SelectBuilder`] to run it.
+    pub fn select(vars: &[&str]) -> SelectBuilder {
+        SelectBuilder::new(vars)
+    }
 }
 
 impl SparqlDataset for SparqlClient {
@@ -121,20 +454,47 @@ impl SparqlDataset for SparqlClient {
         Q: ToQuery<Query>,
     {
         let query = query.to_query()?;
-        let resp = self
-            .agent
-            .post(&self.endpoint)
-            .set("Accept", self.accept())
-            .set("Content-type", "application/sparql-query")
-            .send_string(&query.borrow().0)?;
+        let query_text = self.prepare_query_text(&query.borrow().0);
+        let resp = self.send_with_retry(|| match self.effective_transport(query_text.len()) {
+            Transport::Get => {
+                let req = self
+                    .apply_common(self.agent.get(&self.endpoint).set("Accept", self.accept()));
+                self.apply_graph_params(req)
+                    .query("query", &query_text)
+                    .call()
+            }
+            Transport::PostDirect => {
+                let req = self
+                    .apply_common(self.agent.post(&self.endpoint).set("Accept", self.accept()))
+                    .set("Content-type", "application/sparql-query");
+                self.apply_graph_params(req).send_string(&query_text)
+            }
+            Transport::PostForm => {
+                let mut form = vec![("query", query_text.as_ref())];
+                for g in &self.default_graphs {
+                    form.push(("default-graph-uri", g.as_ref()));
+                }
+                for g in &self.named_graphs {
+                    form.push(("named-graph-uri", g.as_ref()));
+                }
+                let req = self
+                    .apply_common(self.agent.post(&self.endpoint).set("Accept", self.accept()));
+                req.send_form(&form)
+            }
+        })?;
         use ResultsDocument::*;
         match resp.content_type() {
-            "application/sparql-results+json" => match resp.into_json::<ResultsDocument>()? {
-                Boolean { boolean, .. } => Ok(SparqlResult::Boolean(boolean)),
-                Bindings { doc } => Ok(SparqlResult::Bindings(doc)),
-            },
+            "application/sparql-results+json" => {
+                match results_json::parse(resp.into_reader())? {
+                    Boolean(boolean) => Ok(SparqlResult::Boolean(boolean)),
+                    Bindings(doc) => Ok(SparqlResult::Bindings(doc)),
+                }
+            }
             "application/sparql-results+xml" => {
-                todo!("XML bindings not supported yet")
+                match results_xml::parse(BufReader::new(resp.into_reader()))? {
+                    Boolean(boolean) => Ok(SparqlResult::Boolean(boolean)),
+                    Bindings(doc) => Ok(SparqlResult::Bindings(doc)),
+                }
             }
             "text/turtle" => {
                 Self::wrap_triple_source(turtle::parse_bufread(BufReader::new(resp.into_reader())))
@@ -155,23 +515,7 @@ impl SparqlDataset for SparqlClient {
 
 impl SparqlBindings<SparqlClient> for Bindings {
     fn variables(&self) -> Vec<&str> {
-        self.head
-            .vars
-            .iter()
-            .map(|b| b.as_ref())
-            .collect::<Vec<&str>>()
-    }
-}
-
-impl Iterator for Bindings {
-    type Item = Result<Vec<Option<BoxTerm>>, Error>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.results.bindings.is_empty() {
-            None
-        } else {
-            Some(self.pop_binding())
-        }
+        self.variables().iter().map(AsRef::as_ref).collect()
     }
 }
 
@@ -205,6 +549,22 @@ pub enum Error {
         #[from]
         rio_xml::RdfXmlError,
     ),
+    #[error("SPARQL results XML parsing error: {0}")]
+    ResultsXml(
+        #[source]
+        #[from]
+        quick_xml::Error,
+    ),
+    #[error("SPARQL results JSON parsing error: {0}")]
+    ResultsJson(
+        #[source]
+        #[from]
+        serde_json::Error,
+    ),
+    #[error("this endpoint does not support SPARQL Update")]
+    UpdateNotSupported,
+    #[error("cannot bind term as SPARQL syntax: {0}")]
+    InvalidBinding(String),
 }
 
 impl From<UreqError> for Error {
@@ -228,6 +588,321 @@ impl SparqlQuery for Query {
     }
 }
 
+/// A query template whose `?var`/`$var` placeholders can be bound to
+/// concrete terms before execution, via [`PreparedQuery::bind`].
+///
+/// Built with [`SparqlClient::prepare`]. Each bound value is serialized as
+/// proper SPARQL term syntax (an IRI in `<>`, an escaped and typed/tagged
+/// literal, or a `_:label` blank node) rather than interpolated as-is, so
+/// binding untrusted values (e.g. user input) is safe.
+pub struct PreparedQuery {
+    template: Box<str>,
+    base_iri: Option<Box<str>>,
+    values: HashMap<Box<str>, Box<str>>,
+}
+
+impl PreparedQuery {
+    fn new(query: &str, base_iri: Option<&str>) -> Self {
+        PreparedQuery {
+            template: Box::from(query),
+            base_iri: base_iri.map(Box::from),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Bind `var` (without its leading `?`/`$`) to `term` for the next
+    /// execution of this query.
+    pub fn bind<T: TTerm + ?Sized>(&mut self, var: &str, term: &T) -> Result<&mut Self, Error> {
+        self.values.insert(Box::from(var), serialize_term(term)?);
+        Ok(self)
+    }
+
+    /// Remove any binding previously set for `var`, turning it back into a
+    /// free variable.
+    pub fn unbind(&mut self, var: &str) -> &mut Self {
+        self.values.remove(var);
+        self
+    }
+
+    fn render(&self) -> String {
+        let substituted = substitute_variables(&self.template, &self.values);
+        match &self.base_iri {
+            Some(base) => format!("BASE <{}>\n{}", base, substituted),
+            None => substituted,
+        }
+    }
+}
+
+impl ToQuery<Query> for &PreparedQuery {
+    fn to_query(self) -> Result<Query, Error> {
+        Query::parse(&self.render())
+    }
+}
+
+/// Replace every `?var`/`$var` placeholder in `template` that has a matching
+/// entry in `values`, skipping over comments, `<...>` IRIREFs and quoted
+/// literals (including triple-quoted `"""..."""`/`'''...'''` long literals)
+/// so that `?`/`$`/`#` occurring inside them is left untouched.
+fn substitute_variables(template: &str, values: &HashMap<Box<str>, Box<str>>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '<' => {
+                // Tentatively match an IRIREF: '<' followed by characters
+                // none of which are forbidden by the IRIREF grammar rule, up
+                // to the next '>'. The character class alone can't tell a
+                // real IRIREF from a `<` comparison operator followed later
+                // on the same line by a `>` one (e.g. `FILTER(?x<10&&?y>20)`
+                // is a perfectly legal expression whose span "10&&?y" trips
+                // no forbidden character), so also require a `:` before the
+                // closing `>` — every absolute IRI has one for its scheme.
+                // Known limitation: this also rejects the empty/relative
+                // IRIREF `<>` and other scheme-less relative IRIREFs, which
+                // are valid SPARQL syntax but rare in hand-written templates.
+                let mut lookahead = chars.clone();
+                let mut body = String::new();
+                let mut closed = false;
+                let mut seen_colon = false;
+                while let Some(&(_, c2)) = lookahead.peek() {
+                    if c2 == '>' {
+                        lookahead.next();
+                        closed = seen_colon;
+                        break;
+                    }
+                    if c2 <= '\u{20}' || matches!(c2, '<' | '"' | '{' | '}' | '|' | '^' | '`' | '\\')
+                    {
+                        break;
+                    }
+                    if c2 == ':' {
+                        seen_colon = true;
+                    }
+                    body.push(c2);
+                    lookahead.next();
+                }
+                out.push('<');
+                if closed {
+                    out.push_str(&body);
+                    out.push('>');
+                    chars = lookahead;
+                }
+            }
+            '#' => {
+                out.push(c);
+                for (_, c) in chars.by_ref() {
+                    out.push(c);
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' | '\'' => {
+                // Check for a triple-quoted long string literal.
+                let mut lookahead = chars.clone();
+                let is_triple = matches!(lookahead.next(), Some((_, c2)) if c2 == c)
+                    && matches!(lookahead.next(), Some((_, c3)) if c3 == c);
+                out.push(c);
+                if is_triple {
+                    out.push(c);
+                    out.push(c);
+                    chars = lookahead;
+                    loop {
+                        match chars.next() {
+                            Some((_, '\\')) => {
+                                out.push('\\');
+                                if let Some((_, escaped)) = chars.next() {
+                                    out.push(escaped);
+                                }
+                            }
+                            Some((_, c2)) if c2 == c => {
+                                let mut closing = chars.clone();
+                                if matches!(closing.next(), Some((_, c3)) if c3 == c)
+                                    && matches!(closing.next(), Some((_, c4)) if c4 == c)
+                                {
+                                    out.push(c);
+                                    out.push(c);
+                                    out.push(c);
+                                    chars = closing;
+                                    break;
+                                }
+                                out.push(c2);
+                            }
+                            Some((_, c2)) => out.push(c2),
+                            None => break,
+                        }
+                    }
+                } else {
+                    while let Some((_, c2)) = chars.next() {
+                        out.push(c2);
+                        if c2 == '\\' {
+                            if let Some((_, escaped)) = chars.next() {
+                                out.push(escaped);
+                            }
+                        } else if c2 == c {
+                            break;
+                        }
+                    }
+                }
+            }
+            '?' | '$' if matches!(chars.peek(), Some((_, nc)) if is_var_start(*nc)) => {
+                let mut name = String::new();
+                while let Some(&(_, nc)) = chars.peek() {
+                    if is_var_char(nc) {
+                        name.push(nc);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match values.get(name.as_str()) {
+                    Some(term) => out.push_str(term),
+                    None => {
+                        out.push(c);
+                        out.push_str(&name);
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn is_var_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+pub(crate) fn is_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Reject characters forbidden in an `IRIREF` by the SPARQL grammar:
+/// `[#x00-#x20]` (so this also covers plain spaces, not just control
+/// characters) plus the listed delimiters.
+pub(crate) fn check_iriref_chars(iri: &str) -> Result<(), Error> {
+    if iri.chars().any(|c| {
+        c <= '\u{20}' || matches!(c, '<' | '>' | '"' | '{' | '}' | '|' | '^' | '`' | '\\')
+    }) {
+        return Err(Error::InvalidBinding(format!(
+            "IRI contains characters forbidden in an IRIREF: {}",
+            iri
+        )));
+    }
+    Ok(())
+}
+
+/// Reject characters forbidden in a `BLANK_NODE_LABEL` by the SPARQL
+/// grammar, restricted here to the conservative `[A-Za-z0-9_.-]` subset of
+/// `PN_CHARS` (rather than its full Unicode range) so that a label can never
+/// break out of its `_:label` position.
+pub(crate) fn check_blank_node_label_chars(label: &str) -> Result<(), Error> {
+    let valid = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(Error::InvalidBinding(format!(
+            "blank node label contains characters forbidden in a BLANK_NODE_LABEL: {}",
+            label
+        )));
+    }
+    Ok(())
+}
+
+/// Serialize `term` as SPARQL term syntax, suitable for substitution into a
+/// query's graph pattern.
+///
+/// `term` is generic over any [`TTerm`] implementation, not just ones built
+/// through sophia's own validating constructors, so every part interpolated
+/// into the output (the IRI, the blank node label, the literal's language
+/// tag and datatype IRI) is validated here rather than trusted as-is.
+pub(crate) fn serialize_term<T: TTerm + ?Sized>(term: &T) -> Result<Box<str>, Error> {
+    let text = match term.kind() {
+        TermKind::Iri => {
+            let iri = term.value();
+            check_iriref_chars(&iri)?;
+            format!("<{}>", iri)
+        }
+        TermKind::BlankNode => {
+            let label = term.value();
+            check_blank_node_label_chars(&label)?;
+            format!("_:{}", label)
+        }
+        TermKind::Literal => {
+            let value = escape_literal(&term.value());
+            match (term.language(), term.datatype()) {
+                (Some(lang), _) => {
+                    if lang.is_empty()
+                        || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                    {
+                        return Err(Error::InvalidBinding(format!(
+                            "language tag contains characters forbidden in a LANGTAG: {}",
+                            lang
+                        )));
+                    }
+                    format!("\"{}\"@{}", value, lang)
+                }
+                (None, Some(dt)) if dt.value() != "http://www.w3.org/2001/XMLSchema#string" => {
+                    let dt_iri = dt.value();
+                    check_iriref_chars(&dt_iri)?;
+                    format!("\"{}\"^^<{}>", value, dt_iri)
+                }
+                (None, _) => format!("\"{}\"", value),
+            }
+        }
+        TermKind::Variable => {
+            return Err(Error::InvalidBinding(
+                "cannot bind a variable as a substitution value".into(),
+            ))
+        }
+    };
+    Ok(text.into_boxed_str())
+}
+
+/// Escape a literal's lexical value per the `STRING_LITERAL_QUOTE` grammar
+/// rule, so it can be safely wrapped in `"..."`.
+pub(crate) fn escape_literal(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Encode `input` as standard, padded base64 (RFC 4648), as required for the
+/// `Basic` HTTP authentication scheme's credentials.
+fn base64_encode(input: &str) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = chunk.get(1).copied().unwrap_or(0);
+        let b2 = chunk.get(2).copied().unwrap_or(0);
+        out.push(ALPHABET[(b0 >> 2) as usize] as char);
+        out.push(ALPHABET[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[(b2 & 0x3f) as usize] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,6 +1027,239 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn prepared_query_substitutes_and_escapes() -> TestResult {
+        let mut q = PreparedQuery::new(
+            "PREFIX : <tag:> SELECT ?x WHERE { ?x :label $label }",
+            None,
+        );
+        q.bind("x", &BoxTerm::new_iri("tag:a")?)?;
+        q.bind("label", &BoxTerm::new_literal_lang("a \"quote\"", "en")?)?;
+        assert_eq!(
+            q.render(),
+            r#"PREFIX : <tag:> SELECT <tag:a> WHERE { <tag:a> :label "a \"quote\""@en }"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_query_leaves_vars_inside_literals_untouched() -> TestResult {
+        let mut q = PreparedQuery::new(r#"SELECT ?x WHERE { ?x :label "not ?x" }"#, None);
+        q.bind("x", &BoxTerm::new_iri("tag:a")?)?;
+        assert_eq!(
+            q.render(),
+            r#"SELECT <tag:a> WHERE { <tag:a> :label "not ?x" }"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_query_substitutes_after_hash_inside_iriref() -> TestResult {
+        let mut q = PreparedQuery::new(
+            "SELECT ?x WHERE { ?x <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> ?type }",
+            None,
+        );
+        q.bind("x", &BoxTerm::new_iri("tag:a")?)?;
+        q.bind("type", &BoxTerm::new_iri("tag:a")?)?;
+        assert_eq!(
+            q.render(),
+            "SELECT <tag:a> WHERE { <tag:a> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <tag:a> }"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_query_leaves_vars_inside_triple_quoted_literals_untouched() -> TestResult {
+        let mut q = PreparedQuery::new(r#"SELECT ?x WHERE { ?x :label """a "quote before ?x""" }"#, None);
+        q.bind("x", &BoxTerm::new_iri("tag:a")?)?;
+        assert_eq!(
+            q.render(),
+            r#"SELECT <tag:a> WHERE { <tag:a> :label """a "quote before ?x""" }"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_query_substitutes_var_after_filter_comparison_operators() -> TestResult {
+        // `?n<10&&?y>20` is a legal FILTER expression, not an IRIREF: the
+        // span between the two angle brackets ("10&&?y") contains no `:`,
+        // so it must not be swallowed as a match and must leave `?y` free
+        // to be substituted.
+        let mut q = PreparedQuery::new(
+            "SELECT ?x WHERE { ?x :p ?n . FILTER(?n<10&&?y>20) }",
+            None,
+        );
+        q.bind("y", &BoxTerm::new_literal_dt("5", xsd::integer)?)?;
+        assert_eq!(
+            q.render(),
+            r#"SELECT ?x WHERE { ?x :p ?n . FILTER(?n<10&&"5"^^<http://www.w3.org/2001/XMLSchema#integer>>20) }"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn base64_encode_rfc4648_vectors() {
+        // https://tools.ietf.org/html/rfc4648#section-10
+        assert_eq!(base64_encode(""), "");
+        assert_eq!(base64_encode("f"), "Zg==");
+        assert_eq!(base64_encode("fo"), "Zm8=");
+        assert_eq!(base64_encode("foo"), "Zm9v");
+        assert_eq!(base64_encode("foob"), "Zm9vYg==");
+        assert_eq!(base64_encode("fooba"), "Zm9vYmE=");
+        assert_eq!(base64_encode("foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn check_blank_node_label_chars_rejects_forbidden_chars() {
+        assert!(check_blank_node_label_chars("abc123").is_ok());
+        assert!(check_blank_node_label_chars("a_b-c.d").is_ok());
+        assert!(check_blank_node_label_chars("").is_err());
+        assert!(check_blank_node_label_chars("a b").is_err());
+        assert!(check_blank_node_label_chars("a>b . } DELETE WHERE { ?s ?p ?o } #").is_err());
+        assert!(check_blank_node_label_chars("a#b").is_err());
+    }
+
+    #[test]
+    fn effective_transport_without_threshold_is_unchanged() {
+        let cli = client().with_transport(Transport::Get);
+        assert_eq!(cli.effective_transport(1_000_000), Transport::Get);
+    }
+
+    #[test]
+    fn effective_transport_switches_get_to_post_past_threshold() {
+        let cli = client()
+            .with_transport(Transport::Get)
+            .with_auto_post_threshold(100);
+        assert_eq!(cli.effective_transport(50), Transport::Get);
+        assert_eq!(cli.effective_transport(150), Transport::PostDirect);
+    }
+
+    #[test]
+    fn effective_transport_ignores_threshold_for_post_transports() {
+        let cli = client()
+            .with_transport(Transport::PostForm)
+            .with_auto_post_threshold(10);
+        assert_eq!(cli.effective_transport(1000), Transport::PostForm);
+    }
+
+    #[test]
+    fn prepare_query_text_prepends_base_when_set() {
+        let cli = client().with_base_iri("http://example.org/");
+        assert_eq!(
+            cli.prepare_query_text("SELECT * {}").as_ref(),
+            "BASE <http://example.org/>\nSELECT * {}"
+        );
+    }
+
+    #[test]
+    fn prepare_query_text_leaves_query_unchanged_without_base() {
+        let cli = client();
+        assert_eq!(cli.prepare_query_text("SELECT * {}").as_ref(), "SELECT * {}");
+    }
+
+    #[test]
+    fn apply_graph_params_adds_default_and_named_graphs() {
+        let cli = client()
+            .with_default_graph("http://example.org/g1")
+            .with_named_graph("http://example.org/g2")
+            .with_named_graph("http://example.org/g3");
+        let req = cli.apply_graph_params(cli.agent.get("http://example.invalid/"));
+        let url = req.url();
+        assert_eq!(url.matches("default-graph-uri=").count(), 1);
+        assert_eq!(url.matches("named-graph-uri=").count(), 2);
+    }
+
+    #[test]
+    fn apply_graph_params_without_graphs_is_a_no_op() {
+        let cli = client();
+        let req = cli.apply_graph_params(cli.agent.get("http://example.invalid/"));
+        assert_eq!(req.url(), "http://example.invalid/");
+    }
+
+    fn fake_response(status: u16) -> Response {
+        Response::new(status, "status", "body").unwrap()
+    }
+
+    #[test]
+    fn apply_common_sets_user_agent_headers_and_bearer_auth() {
+        let cli = client()
+            .with_user_agent("test-agent/1.0")
+            .with_header("X-Custom", "value")
+            .with_bearer_auth("tok123")
+            .with_timeout(Duration::from_secs(5));
+        let req = cli.apply_common(cli.agent.get("http://example.invalid/"));
+        assert_eq!(req.header("User-Agent"), Some("test-agent/1.0"));
+        assert_eq!(req.header("X-Custom"), Some("value"));
+        assert_eq!(req.header("Authorization"), Some("Bearer tok123"));
+    }
+
+    #[test]
+    fn apply_common_basic_auth_is_base64_encoded() {
+        let cli = client().with_basic_auth("alice", "s3cret");
+        let req = cli.apply_common(cli.agent.get("http://example.invalid/"));
+        let expected = format!("Basic {}", base64_encode("alice:s3cret"));
+        assert_eq!(req.header("Authorization"), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn apply_common_without_auth_leaves_authorization_unset() {
+        let cli = client();
+        let req = cli.apply_common(cli.agent.get("http://example.invalid/"));
+        assert_eq!(req.header("Authorization"), None);
+    }
+
+    #[test]
+    fn send_with_retry_retries_429_then_succeeds() {
+        let cli = client().with_retry(RetryPolicy::exponential(3, Duration::from_millis(0)));
+        let attempts = std::cell::Cell::new(0u32);
+        let result = cli.send_with_retry(|| {
+            let n = attempts.get();
+            attempts.set(n + 1);
+            if n < 2 {
+                Err(UreqError::Status(429, fake_response(429)))
+            } else {
+                Ok(fake_response(200))
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn send_with_retry_gives_up_after_max_retries() {
+        let cli = client().with_retry(RetryPolicy::exponential(2, Duration::from_millis(0)));
+        let attempts = std::cell::Cell::new(0u32);
+        let result = cli.send_with_retry(|| {
+            attempts.set(attempts.get() + 1);
+            Err(UreqError::Status(503, fake_response(503)))
+        });
+        assert!(matches!(result, Err(UreqError::Status(503, _))));
+        assert_eq!(attempts.get(), 3); // initial attempt + 2 retries
+    }
+
+    #[test]
+    fn send_with_retry_does_not_retry_other_statuses() {
+        let cli = client().with_retry(RetryPolicy::exponential(5, Duration::from_millis(0)));
+        let attempts = std::cell::Cell::new(0u32);
+        let result = cli.send_with_retry(|| {
+            attempts.set(attempts.get() + 1);
+            Err(UreqError::Status(404, fake_response(404)))
+        });
+        assert!(matches!(result, Err(UreqError::Status(404, _))));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn update_insert_then_ask() -> TestResult {
+        let cli = client();
+        cli.update("PREFIX : <tag:> INSERT DATA { :update_s :update_p :update_o }")?;
+        match cli.query("PREFIX : <tag:> ASK { :update_s :update_p :update_o }")? {
+            Boolean(true) => (),
+            _ => assert!(false),
+        };
+        Ok(())
+    }
+
     #[test]
     fn construct_empty() -> TestResult {
         test_construct(client(), "")
@@ -1,34 +1,20 @@
 use super::Error;
+use crate::results_json::Event;
 use serde::{Deserialize, Serialize};
 use sophia::ns::xsd;
 use sophia::term::BoxTerm;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::ops::{Deref, Index};
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::thread::JoinHandle;
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-#[serde(untagged)]
-pub enum ResultsDocument {
-    Boolean {
-        head: BooleanHead,
-        boolean: bool,
-    },
-    Bindings {
-        #[serde(flatten)]
-        doc: BindingsDocument,
-    },
-}
-
-/// The result of a `SELECT` query as returned by [`SparqlClient`](super::SparqlClient).
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-pub struct BindingsDocument {
-    pub(super) head: BindingsHead,
-    pub(super) results: Results,
-}
-
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-pub struct BooleanHead {
-    #[serde(default)]
-    link: Vec<Box<str>>,
+/// The outcome of a `SELECT`/`ASK` query, before it has been matched against
+/// [`SparqlResult`](sophia::sparql::SparqlResult) by [`SparqlClient`](super::SparqlClient).
+pub(super) enum ResultsDocument {
+    Boolean(bool),
+    Bindings(BindingsDocument),
 }
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
@@ -88,18 +74,140 @@ impl TryFrom<Term> for BoxTerm {
     }
 }
 
+/// The result of a `SELECT` query, as returned by [`SparqlClient`](super::SparqlClient).
+///
+/// Rows are pulled one at a time (see [`Iterator for BindingsDocument`](#impl-Iterator-for-BindingsDocument))
+/// from a background thread parsing the response as it is still being
+/// received, for both the JSON ([`results_json`](super::results_json)) and
+/// XML ([`results_xml`](super::results_xml)) results formats.
+pub struct BindingsDocument {
+    vars: Rc<[Box<str>]>,
+    source: BindingsSource,
+}
+
+enum BindingsSource {
+    Streamed {
+        rx: Receiver<Result<Event, Error>>,
+        // owned but never joined: dropping it detaches the parsing thread
+        _worker: JoinHandle<()>,
+    },
+}
+
 impl BindingsDocument {
-    pub(super) fn pop_binding(&mut self) -> Result<Vec<Option<BoxTerm>>, Error> {
-        debug_assert!(!self.results.bindings.is_empty());
-        let mut hm = self.results.bindings.drain(..1).next().unwrap();
-        let mut v = Vec::<Option<BoxTerm>>::with_capacity(self.head.vars.len());
-        for key in &self.head.vars {
-            match hm.remove(&*key) {
-                None => v.push(None),
-                Some(term) => v.push(Some(term.try_into()?)),
+    /// Build a [`BindingsDocument`] whose bindings are pulled, one at a time,
+    /// from a background thread parsing the response as it arrives.
+    pub(super) fn streamed(
+        vars: Vec<Box<str>>,
+        rx: Receiver<Result<Event, Error>>,
+        worker: JoinHandle<()>,
+    ) -> Self {
+        Self {
+            vars: Rc::from(vars.into_boxed_slice()),
+            source: BindingsSource::Streamed {
+                rx,
+                _worker: worker,
+            },
+        }
+    }
+
+    pub(super) fn variables(&self) -> &[Box<str>] {
+        &self.vars
+    }
+
+    fn build_solution(&self, mut hm: HashMap<Box<str>, Term>) -> Result<Solution, Error> {
+        let mut values = Vec::<Option<BoxTerm>>::with_capacity(self.vars.len());
+        for key in self.vars.iter() {
+            match hm.remove(&**key) {
+                None => values.push(None),
+                Some(term) => values.push(Some(term.try_into()?)),
             }
         }
-        Ok(v)
+        Ok(Solution {
+            vars: Rc::clone(&self.vars),
+            values,
+        })
+    }
+}
+
+impl Iterator for BindingsDocument {
+    type Item = Result<Solution, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let hm = match &mut self.source {
+            BindingsSource::Streamed { rx, .. } => loop {
+                match rx.recv() {
+                    Ok(Ok(Event::Binding(hm))) => break Some(Ok(hm)),
+                    Ok(Ok(Event::Init(_))) => continue, // the head was already consumed; ignore
+                    Ok(Err(e)) => break Some(Err(e)),
+                    Err(_) => break None, // the worker thread is done: end of stream
+                }
+            },
+        }?;
+        Some(hm.and_then(|hm| self.build_solution(hm)))
+    }
+}
+
+/// A single row of a `SELECT` result set.
+///
+/// Besides the positional [`Index`] and [`Deref`] to `Vec<Option<BoxTerm>>`
+/// (kept for code written against the old positional API), a [`Solution`]
+/// also gives named access to its bound terms via [`Solution::get`].
+#[derive(Debug, PartialEq)]
+pub struct Solution {
+    vars: Rc<[Box<str>]>,
+    values: Vec<Option<BoxTerm>>,
+}
+
+impl Solution {
+    /// The term bound to `var` in this solution, or `None` if `var` is not
+    /// projected by the query, or is unbound in this particular solution.
+    pub fn get(&self, var: &str) -> Option<&BoxTerm> {
+        self.vars
+            .iter()
+            .position(|v| &**v == var)
+            .and_then(|i| self.values[i].as_ref())
+    }
+
+    /// The term bound at position `index`, or `None` if unbound.
+    pub fn get_by_index(&self, index: usize) -> Option<&BoxTerm> {
+        self.values.get(index).and_then(|t| t.as_ref())
+    }
+
+    /// Iterate over `(variable, term)` pairs, in projection order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&BoxTerm>)> {
+        self.vars
+            .iter()
+            .map(AsRef::as_ref)
+            .zip(self.values.iter().map(Option::as_ref))
+    }
+
+    /// The number of variables projected by the query (bound or not).
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// `true` if the query this solution comes from projects no variable.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The ordered list of variables projected by the query this solution comes from.
+    pub fn variables(&self) -> &[Box<str>] {
+        &self.vars
+    }
+}
+
+impl Deref for Solution {
+    type Target = Vec<Option<BoxTerm>>;
+    fn deref(&self) -> &Self::Target {
+        &self.values
+    }
+}
+
+impl Index<usize> for Solution {
+    type Output = Option<BoxTerm>;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.values[index]
     }
 }
 
@@ -249,7 +357,7 @@ mod test_json {
     }
 
     #[test]
-    fn bindings_doc() {
+    fn streamed_bindings_doc() {
         let src = r#"
         {
             "head": {
@@ -265,95 +373,45 @@ mod test_json {
                         "b": {
                             "type": "literal",
                             "value": "simple"
-                        },
-                        "c": {
-                            "type": "bnode",
-                            "value": "bn0"
                         }
                     },
                     {
                         "c": {
-                            "type": "literal",
-                            "value": "datatype",
-                            "datatype": "tag:d1"
-                        },
-                        "a": {
-                            "type": "literal",
-                            "value": "lang",
-                            "xml:lang": "en"
+                            "type": "bnode",
+                            "value": "bn0"
                         }
                     }
                 ]
             }
         }"#;
-        let got: ResultsDocument = serde_json::from_str(src).unwrap();
-        let exp = ResultsDocument::Bindings {
-            doc: BindingsDocument {
-                head: BindingsHead {
-                    vars: vec!["a".into(), "b".into(), "c".into()],
-                    link: vec![],
-                },
-                results: Results {
-                    bindings: vec![
-                        vec![
-                            (
-                                "a".into(),
-                                Term::Uri {
-                                    value: "tag:a0".into(),
-                                },
-                            ),
-                            (
-                                "b".into(),
-                                Term::Literal(Literal::Simple {
-                                    value: "simple".into(),
-                                }),
-                            ),
-                            (
-                                "c".into(),
-                                Term::Bnode {
-                                    value: "bn0".into(),
-                                },
-                            ),
-                        ]
-                        .into_iter()
-                        .collect::<HashMap<Box<str>, Term>>(),
-                        vec![
-                            (
-                                "c".into(),
-                                Term::Literal(Literal::Datatype {
-                                    value: "datatype".into(),
-                                    datatype: "tag:d1".into(),
-                                }),
-                            ),
-                            (
-                                "a".into(),
-                                Term::Literal(Literal::Lang {
-                                    value: "lang".into(),
-                                    lang: "en".into(),
-                                }),
-                            ),
-                        ]
-                        .into_iter()
-                        .collect::<HashMap<Box<str>, Term>>(),
-                    ],
-                },
-            },
+        let doc = match crate::results_json::parse(src.as_bytes()).unwrap() {
+            ResultsDocument::Bindings(doc) => doc,
+            ResultsDocument::Boolean(_) => panic!("expected Bindings"),
         };
-        assert_eq!(got, exp);
+        assert_eq!(doc.variables(), &["a".into(), "b".into(), "c".into()] as &[Box<str>]);
+        let rows = doc.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get("a"), Some(&BoxTerm::new_iri("tag:a0").unwrap()));
+        assert_eq!(
+            rows[0].get("b"),
+            Some(&BoxTerm::new_literal_dt("simple", xsd::string).unwrap())
+        );
+        assert_eq!(rows[0].get("c"), None);
+        assert_eq!(rows[1].get("a"), None);
+        assert_eq!(
+            rows[1].get("c"),
+            Some(&BoxTerm::new_bnode("bn0").unwrap())
+        );
     }
 
     #[test]
-    fn boolean_doc() {
+    fn streamed_boolean_doc() {
         let src = r#"
         {
             "head": {},
             "boolean": true
         }"#;
-        let got: ResultsDocument = serde_json::from_str(src).unwrap();
-        let exp = ResultsDocument::Boolean {
-            head: BooleanHead { link: vec![] },
-            boolean: true,
-        };
-        assert_eq!(got, exp);
+        let got = crate::results_json::parse(src.as_bytes()).unwrap();
+        assert!(matches!(got, ResultsDocument::Boolean(true)));
     }
 }
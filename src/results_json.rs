@@ -0,0 +1,195 @@
+//! A streaming parser for the SPARQL Results JSON format.
+//!
+//! A plain `serde_json::from_reader::<ResultsDocument>()` would have to
+//! buffer the whole `results.bindings` array before a single row is visible
+//! to the caller. Instead, [`parse`] drives `serde_json` through a handful
+//! of [`Visitor`]s that read `head` eagerly (it is always small) and then,
+//! for the `results.bindings` array, send one binding at a time down a
+//! channel from a background thread — so [`BindingsDocument`] can start
+//! yielding rows before the server has finished sending the response.
+use super::results::{BindingsDocument, ResultsDocument, Term};
+use crate::Error;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread;
+
+/// A message sent from the parsing thread to the consuming [`BindingsDocument`].
+pub(super) enum Event {
+    /// The (only) `head`/`boolean` pair found at the top of the document.
+    Init(Init),
+    /// One element of the `results.bindings` array.
+    Binding(HashMap<Box<str>, Term>),
+}
+
+pub(super) enum Init {
+    Boolean(bool),
+    Bindings(Vec<Box<str>>),
+}
+
+/// Parse an `application/sparql-results+json` document read incrementally from `reader`.
+pub(super) fn parse<R: Read + Send + 'static>(reader: R) -> Result<ResultsDocument, Error> {
+    let (tx, rx) = sync_channel(0);
+    let worker = thread::spawn(move || {
+        let mut de = serde_json::Deserializer::from_reader(reader);
+        if let Err(e) = de.deserialize_map(RootVisitor { tx: &tx }) {
+            let _ = tx.send(Err(Error::from(e)));
+        }
+    });
+    // The worker sends its single `Init` when it reaches `results` or
+    // `boolean`, before any binding, so it is always the first message; the
+    // variables are those of a `head` found before `results` (as every SPARQL
+    // processor we know of writes it).
+    match rx.recv() {
+        Ok(Ok(Event::Init(Init::Boolean(b)))) => Ok(ResultsDocument::Boolean(b)),
+        Ok(Ok(Event::Init(Init::Bindings(vars)))) => Ok(ResultsDocument::Bindings(
+            BindingsDocument::streamed(vars, rx, worker),
+        )),
+        Ok(Ok(Event::Binding(_))) => Err(Error::Unsupported(
+            "malformed SPARQL results JSON: `results` before `head`".into(),
+        )),
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            // the worker gave up without sending anything; join it to surface a panic, if any
+            let _ = worker.join();
+            Err(Error::Unsupported(
+                "empty or truncated SPARQL results JSON document".into(),
+            ))
+        }
+    }
+}
+
+/// The `head` object; `vars` is absent from the head of a boolean result.
+#[derive(Deserialize)]
+struct Head {
+    #[serde(default)]
+    vars: Vec<Box<str>>,
+}
+
+struct RootVisitor<'a> {
+    tx: &'a SyncSender<Result<Event, Error>>,
+}
+
+impl<'de, 'a> Visitor<'de> for RootVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a SPARQL results JSON document")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // `head` is held back until `results` or `boolean` tells a `SELECT`
+        // result from an `ASK` one, so that exactly one `Init` is sent.
+        let mut vars = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "head" => {
+                    let head: Head = map.next_value()?;
+                    vars = Some(head.vars);
+                }
+                "boolean" => {
+                    let boolean: bool = map.next_value()?;
+                    let _ = self.tx.send(Ok(Event::Init(Init::Boolean(boolean))));
+                }
+                "results" => {
+                    let vars = vars.take().unwrap_or_default();
+                    let _ = self.tx.send(Ok(Event::Init(Init::Bindings(vars))));
+                    map.next_value_seed(ResultsSeed { tx: self.tx })?;
+                }
+                _ => {
+                    let _: IgnoredAny = map.next_value()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ResultsSeed<'a> {
+    tx: &'a SyncSender<Result<Event, Error>>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for ResultsSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ResultsVisitor { tx: self.tx })
+    }
+}
+
+struct ResultsVisitor<'a> {
+    tx: &'a SyncSender<Result<Event, Error>>,
+}
+
+impl<'de, 'a> Visitor<'de> for ResultsVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a `results` object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "bindings" {
+                map.next_value_seed(BindingsSeed { tx: self.tx })?;
+            } else {
+                let _: IgnoredAny = map.next_value()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct BindingsSeed<'a> {
+    tx: &'a SyncSender<Result<Event, Error>>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for BindingsSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(BindingsVisitor { tx: self.tx })
+    }
+}
+
+struct BindingsVisitor<'a> {
+    tx: &'a SyncSender<Result<Event, Error>>,
+}
+
+impl<'de, 'a> Visitor<'de> for BindingsVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of binding objects")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while let Some(binding) = seq.next_element::<HashMap<Box<str>, Term>>()? {
+            if self.tx.send(Ok(Event::Binding(binding))).is_err() {
+                // the receiver was dropped: the caller stopped iterating early.
+                // Keep draining so the underlying parse finishes cleanly.
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                break;
+            }
+        }
+        Ok(())
+    }
+}
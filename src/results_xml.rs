@@ -0,0 +1,305 @@
+//! A parser for the [SPARQL Query Results XML Format].
+//!
+//! This mirrors the JSON path in [`super::results_json`]: both end up
+//! building the same [`ResultsDocument`] by driving the underlying parser
+//! from a background thread and sending one binding at a time down a
+//! channel, so [`BindingsDocument`] can start yielding rows before the
+//! server has finished sending the response, rather than buffering the
+//! whole `<results>` element first.
+//!
+//! [SPARQL Query Results XML Format]: https://www.w3.org/TR/rdf-sparql-XMLres/
+use super::results::{BindingsDocument, Literal, ResultsDocument, Term};
+use crate::results_json::{Event as StreamEvent, Init};
+use crate::Error;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread;
+
+/// Parse an `application/sparql-results+xml` document read incrementally from `source`.
+pub(super) fn parse<R: BufRead + Send + 'static>(source: R) -> Result<ResultsDocument, Error> {
+    let (tx, rx) = sync_channel(0);
+    let worker = thread::spawn(move || {
+        if let Err(e) = run(source, &tx) {
+            let _ = tx.send(Err(e));
+        }
+    });
+    // The worker sends its single `Init` when `<results>` or `<boolean>`
+    // starts, after `head` (as every SPARQL processor we know of writes it),
+    // so it is always the first message.
+    match rx.recv() {
+        Ok(Ok(StreamEvent::Init(Init::Boolean(b)))) => Ok(ResultsDocument::Boolean(b)),
+        Ok(Ok(StreamEvent::Init(Init::Bindings(vars)))) => Ok(ResultsDocument::Bindings(
+            BindingsDocument::streamed(vars, rx, worker),
+        )),
+        Ok(Ok(StreamEvent::Binding(_))) => Err(Error::Unsupported(
+            "malformed SPARQL results XML: <result> before </head>".into(),
+        )),
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            // the worker gave up without sending anything; join it to surface a panic, if any
+            let _ = worker.join();
+            Err(Error::Unsupported(
+                "empty or truncated SPARQL results XML document".into(),
+            ))
+        }
+    }
+}
+
+/// Drive `reader` to completion, sending an [`Init`] once `<results>` (or a
+/// top-level `<boolean>`) is seen and then one [`StreamEvent::Binding`] per
+/// `<result>` element.
+fn run<R: BufRead>(source: R, tx: &SyncSender<Result<StreamEvent, Error>>) -> Result<(), Error> {
+    let mut reader = Reader::from_reader(source);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut vars = Vec::<Box<str>>::new();
+
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) if e.name() == b"variable" => {
+                vars.push(attr(e, b"name")?.into_boxed_str());
+            }
+            Event::Start(ref e) | Event::Empty(ref e) if e.name() == b"results" => {
+                if tx
+                    .send(Ok(StreamEvent::Init(Init::Bindings(std::mem::take(
+                        &mut vars,
+                    )))))
+                    .is_err()
+                {
+                    return Ok(()); // the receiver was dropped: the caller gave up
+                }
+            }
+            Event::Start(ref e) if e.name() == b"boolean" => {
+                let text = reader.read_text(e.name(), &mut Vec::new())?;
+                let _ = tx.send(Ok(StreamEvent::Init(Init::Boolean(text == "true"))));
+                return Ok(());
+            }
+            Event::Start(ref e) if e.name() == b"result" => {
+                let binding = parse_result(&mut reader)?;
+                if tx.send(Ok(StreamEvent::Binding(binding))).is_err() {
+                    // the receiver was dropped: the caller stopped iterating early.
+                    break;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+/// Parse the children of a single `<result>` element, up to its closing tag.
+fn parse_result<R: BufRead>(reader: &mut Reader<R>) -> Result<HashMap<Box<str>, Term>, Error> {
+    let mut buf = Vec::new();
+    let mut out = HashMap::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(ref e) if e.name() == b"binding" => {
+                let name = attr(e, b"name")?;
+                out.insert(name.into_boxed_str(), parse_binding_value(reader)?);
+            }
+            Event::End(ref e) if e.name() == b"result" => break,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(out)
+}
+
+/// Parse the single `<uri>`/`<bnode>`/`<literal>` child of a `<binding>` element,
+/// consuming up to (and including) the closing `</binding>` tag.
+fn parse_binding_value<R: BufRead>(reader: &mut Reader<R>) -> Result<Term, Error> {
+    let mut buf = Vec::new();
+    let term = loop {
+        let event = reader.read_event(&mut buf)?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                let term = match e.name() {
+                    b"uri" => Term::Uri {
+                        value: read_text_or_empty(reader, e.name(), is_empty)?,
+                    },
+                    b"bnode" => Term::Bnode {
+                        value: read_text_or_empty(reader, e.name(), is_empty)?,
+                    },
+                    b"literal" => {
+                        let datatype = attr_opt(e, b"datatype")?;
+                        let lang = attr_opt(e, b"xml:lang")?;
+                        let value = read_text_or_empty(reader, e.name(), is_empty)?;
+                        Term::Literal(match (datatype, lang) {
+                            (Some(datatype), None) => Literal::Datatype {
+                                value,
+                                datatype: datatype.into_boxed_str(),
+                            },
+                            (None, Some(lang)) => Literal::Lang {
+                                value,
+                                lang: lang.into_boxed_str(),
+                            },
+                            (None, None) => Literal::Simple { value },
+                            (Some(_), Some(_)) => {
+                                return Err(Error::Unsupported(
+                                    "a <literal> can not have both datatype and xml:lang".into(),
+                                ))
+                            }
+                        })
+                    }
+                    other => {
+                        return Err(Error::Unsupported(format!(
+                            "unexpected element inside <binding>: {}",
+                            String::from_utf8_lossy(other)
+                        )))
+                    }
+                };
+                break term;
+            }
+            Event::Eof => return Err(Error::Unsupported("truncated <binding> element".into())),
+            _ => {}
+        }
+        buf.clear();
+    };
+    // consume the matching `</binding>`
+    buf.clear();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::End(ref e) if e.name() == b"binding" => break,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(term)
+}
+
+/// Read the text content of the element named `name`, started by the event
+/// just consumed from `reader`. If that event was self-closing (`<uri/>`),
+/// there is no separate closing tag to read up to, so the content is simply
+/// empty.
+fn read_text_or_empty<R: BufRead>(
+    reader: &mut Reader<R>,
+    name: &[u8],
+    is_empty: bool,
+) -> Result<Box<str>, Error> {
+    if is_empty {
+        Ok(Box::from(""))
+    } else {
+        Ok(reader.read_text(name, &mut Vec::new())?.into_boxed_str())
+    }
+}
+
+fn attr(e: &BytesStart, key: &[u8]) -> Result<String, Error> {
+    attr_opt(e, key)?.ok_or_else(|| {
+        Error::Unsupported(format!(
+            "missing `{}` attribute",
+            String::from_utf8_lossy(key)
+        ))
+    })
+}
+
+fn attr_opt(e: &BytesStart, key: &[u8]) -> Result<Option<String>, Error> {
+    for a in e.attributes() {
+        let a = a.map_err(|e| Error::Unsupported(e.to_string()))?;
+        if a.key == key {
+            let unescaped = quick_xml::escape::unescape(&a.value)
+                .map_err(|e| Error::Unsupported(e.to_string()))?;
+            return Ok(Some(String::from_utf8_lossy(&unescaped).into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sophia::term::BoxTerm;
+
+    #[test]
+    fn parse_streams_bindings_as_they_are_read() {
+        let src: &'static [u8] = br#"<?xml version="1.0"?>
+<sparql xmlns="http://www.w3.org/2005/sparql-results#">
+  <head>
+    <variable name="a"/>
+    <variable name="b"/>
+  </head>
+  <results>
+    <result>
+      <binding name="a"><uri>tag:a0</uri></binding>
+      <binding name="b"><literal>simple</literal></binding>
+    </result>
+    <result>
+      <binding name="a"><bnode>bn0</bnode></binding>
+    </result>
+  </results>
+</sparql>"#;
+        let doc = match parse(src).unwrap() {
+            ResultsDocument::Bindings(doc) => doc,
+            ResultsDocument::Boolean(_) => panic!("expected Bindings"),
+        };
+        assert_eq!(doc.variables(), &["a".into(), "b".into()] as &[Box<str>]);
+        let rows = doc.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get("a"), Some(&BoxTerm::new_iri("tag:a0").unwrap()));
+        assert_eq!(
+            rows[0].get("b"),
+            Some(&BoxTerm::new_literal_dt("simple", sophia::ns::xsd::string).unwrap())
+        );
+        assert_eq!(rows[1].get("a"), Some(&BoxTerm::new_bnode("bn0").unwrap()));
+        assert_eq!(rows[1].get("b"), None);
+    }
+
+    #[test]
+    fn parse_streams_boolean_result() {
+        let src: &'static [u8] = br#"<?xml version="1.0"?>
+<sparql xmlns="http://www.w3.org/2005/sparql-results#">
+  <head/>
+  <boolean>true</boolean>
+</sparql>"#;
+        let got = parse(src).unwrap();
+        assert!(matches!(got, ResultsDocument::Boolean(true)));
+    }
+
+    fn binding_value(src: &[u8]) -> Term {
+        let mut reader = Reader::from_reader(src);
+        parse_binding_value(&mut reader).unwrap()
+    }
+
+    #[test]
+    fn self_closing_literal_is_empty_string() {
+        assert_eq!(
+            binding_value(b"<literal/></binding>"),
+            Term::Literal(Literal::Simple {
+                value: Box::from("")
+            })
+        );
+    }
+
+    #[test]
+    fn self_closing_uri_and_bnode_are_empty_string() {
+        assert_eq!(
+            binding_value(b"<uri/></binding>"),
+            Term::Uri {
+                value: Box::from("")
+            }
+        );
+        assert_eq!(
+            binding_value(b"<bnode/></binding>"),
+            Term::Bnode {
+                value: Box::from("")
+            }
+        );
+    }
+
+    #[test]
+    fn non_self_closing_literal_is_unaffected() {
+        assert_eq!(
+            binding_value(b"<literal>hello</literal></binding>"),
+            Term::Literal(Literal::Simple {
+                value: Box::from("hello")
+            })
+        );
+    }
+}